@@ -0,0 +1,100 @@
+use bevy::{audio::AudioSink, prelude::*};
+use bevy_rapier3d::prelude::*;
+
+use crate::netcode::LocalPlayer;
+
+/// How fast the marble needs to be moving for the rolling sound to reach
+/// full volume/pitch.
+const MAX_REFERENCE_SPEED: f32 = 20.0;
+
+/// The entities playing the looping music/rolling sounds, and the impact
+/// sample to spawn one-shot copies of.
+#[derive(Resource)]
+struct AudioHandles {
+    music: Entity,
+    rolling: Entity,
+    impact_sound: Handle<AudioSource>,
+}
+
+/// Whether the background music has been muted with the `M` key.
+#[derive(Resource, Default)]
+pub struct MusicMuted(pub bool);
+
+/// Loads the background music and rolling/impact SFX, and starts the music
+/// and rolling loops (the rolling loop starts silent; `update_rolling_audio`
+/// drives its volume from the ball's speed).
+pub fn setup_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let music = commands
+        .spawn(AudioBundle {
+            source: asset_server.load("audio/music.ogg"),
+            settings: PlaybackSettings::LOOP,
+        })
+        .id();
+
+    let rolling = commands
+        .spawn(AudioBundle {
+            source: asset_server.load("audio/rolling.ogg"),
+            settings: PlaybackSettings::LOOP.with_volume(0.0),
+        })
+        .id();
+
+    commands.insert_resource(AudioHandles {
+        music,
+        rolling,
+        impact_sound: asset_server.load("audio/impact.ogg"),
+    });
+    commands.insert_resource(MusicMuted::default());
+}
+
+/// Modulates the rolling sound's volume and playback speed from the
+/// player's current linear velocity: silent at rest, louder and
+/// higher-pitched the faster the marble rolls.
+pub fn update_rolling_audio(
+    handles: Res<AudioHandles>,
+    sinks: Query<&AudioSink>,
+    player: Query<&Velocity, With<LocalPlayer>>,
+) {
+    let Ok(velocity) = player.get_single() else {
+        return;
+    };
+    let Ok(sink) = sinks.get(handles.rolling) else {
+        return;
+    };
+
+    let speed_ratio = (velocity.linvel.length() / MAX_REFERENCE_SPEED).clamp(0.0, 1.0);
+    sink.set_volume(speed_ratio);
+    sink.set_speed(1.0 + speed_ratio * 0.8);
+}
+
+/// Plays a one-shot impact sample whenever the ball starts touching a new
+/// piece of track.
+pub fn play_impact_sfx(
+    mut commands: Commands,
+    handles: Res<AudioHandles>,
+    mut collisions: EventReader<CollisionEvent>,
+) {
+    for event in collisions.read() {
+        if let CollisionEvent::Started(_, _, _) = event {
+            commands.spawn(AudioBundle {
+                source: handles.impact_sound.clone(),
+                settings: PlaybackSettings::DESPAWN,
+            });
+        }
+    }
+}
+
+/// Reads the `M` mute toggle (set by `handle_input`) and silences/restores
+/// the background music sink accordingly.
+pub fn apply_music_mute(
+    muted: Res<MusicMuted>,
+    handles: Res<AudioHandles>,
+    sinks: Query<&AudioSink>,
+) {
+    if !muted.is_changed() {
+        return;
+    }
+
+    if let Ok(sink) = sinks.get(handles.music) {
+        sink.set_volume(if muted.0 { 0.0 } else { 1.0 });
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    window::CursorGrabMode,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::netcode::LocalPlayer;
+
+const MOUSE_SENSITIVITY: f32 = 0.003;
+const ZOOM_SENSITIVITY: f32 = 0.5;
+const MIN_DISTANCE: f32 = 3.0;
+const MAX_DISTANCE: f32 = 20.0;
+const MAX_PITCH: f32 = 1.3;
+
+/// Yaw/pitch/distance for the orbit camera: the mouse drives yaw/pitch while
+/// the cursor is grabbed, and the scroll wheel changes distance at any time.
+#[derive(Resource)]
+pub struct CameraController {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub height: f32,
+    pub cursor_grabbed: bool,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.3,
+            distance: 10.0,
+            height: 5.0,
+            cursor_grabbed: true,
+        }
+    }
+}
+
+/// Grabs or releases the cursor, used both on startup and by `handle_input`'s
+/// `Escape` toggle (alongside the existing `F11` fullscreen handling).
+pub fn apply_cursor_grab(window: &mut Window, grabbed: bool) {
+    window.cursor.grab_mode = if grabbed {
+        CursorGrabMode::Locked
+    } else {
+        CursorGrabMode::None
+    };
+    window.cursor.visible = !grabbed;
+}
+
+/// Applies the initial cursor grab state on startup.
+pub fn setup_camera_controller(mut windows: Query<&mut Window>) {
+    apply_cursor_grab(
+        &mut windows.single_mut(),
+        CameraController::default().cursor_grabbed,
+    );
+}
+
+/// Reads mouse motion/scroll into the orbit camera's yaw/pitch/distance.
+pub fn mouse_look(
+    mut controller: ResMut<CameraController>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+) {
+    if controller.cursor_grabbed {
+        for event in motion.read() {
+            controller.yaw -= event.delta.x * MOUSE_SENSITIVITY;
+            controller.pitch =
+                (controller.pitch - event.delta.y * MOUSE_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+    } else {
+        motion.clear();
+    }
+
+    for event in wheel.read() {
+        controller.distance =
+            (controller.distance - event.y * ZOOM_SENSITIVITY).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+}
+
+/// Orbits the camera around the player using the configured yaw/pitch/
+/// distance, banking with the local track surface (the player's Rapier
+/// contact normal) instead of snapping to world-up through tilted segments.
+pub fn orbit_camera(
+    rapier_context: Res<RapierContext>,
+    controller: Res<CameraController>,
+    mut camera: Query<&mut Transform, With<Camera>>,
+    player: Query<(Entity, &Transform), (With<LocalPlayer>, Without<Camera>)>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+    let Ok((player_entity, player_transform)) = player.get_single() else {
+        return;
+    };
+
+    let up = contact_normal(&rapier_context, player_entity).unwrap_or(Vec3::Y);
+
+    let rotation = Quat::from_axis_angle(up, controller.yaw)
+        * Quat::from_axis_angle(Vec3::X, controller.pitch);
+    let back = rotation * Vec3::Z;
+
+    camera.translation =
+        player_transform.translation + back * controller.distance + up * controller.height;
+    *camera = camera.looking_at(player_transform.translation, up);
+}
+
+/// The surface normal of whatever the player is currently touching, used as
+/// the orbit camera's "up" so it banks naturally through curved/tilted
+/// track segments.
+fn contact_normal(rapier_context: &RapierContext, player: Entity) -> Option<Vec3> {
+    for contact_pair in rapier_context.contacts_with(player) {
+        if let Some(manifold) = contact_pair.manifolds().first() {
+            let normal = if contact_pair.collider1() == player {
+                -manifold.normal
+            } else {
+                manifold.normal
+            };
+            if normal.length_squared() > 0.0 {
+                return Some(normal.normalize());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contact_normal_is_none_without_any_contacts() {
+        let rapier_context = RapierContext::default();
+        assert_eq!(contact_normal(&rapier_context, Entity::from_raw(0)), None);
+    }
+}
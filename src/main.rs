@@ -1,5 +1,3 @@
-use std::f32::consts::PI;
-
 use bevy::{
     asset::LoadState,
     core_pipeline::Skybox,
@@ -7,10 +5,25 @@ use bevy::{
     render::render_resource::{TextureViewDescriptor, TextureViewDimension},
     window::WindowMode,
 };
+use bevy_ggrs::{AddRollbackCommandExtension, GgrsSchedule, ReadInputs};
 use bevy_rapier3d::prelude::*;
 
-#[derive(Component)]
-struct Player;
+mod audio;
+mod camera;
+mod netcode;
+mod render;
+mod sky;
+mod track;
+
+use audio::{apply_music_mute, play_impact_sfx, update_rolling_audio, MusicMuted};
+use camera::{mouse_look, orbit_camera, CameraController};
+use netcode::{
+    apply_rollback_input, configure_match, read_local_input, start_session, LocalPlayer,
+    MatchConfig, NetcodePlugin, PlayerHandle, RollbackState,
+};
+use render::RenderConfig;
+use sky::{update_sky, SkyConfig, SkyMode};
+use track::{check_track_progress, despawn_stale_chunks, generate_track, TrackState};
 
 #[derive(Resource)]
 struct Cubemap {
@@ -18,13 +31,6 @@ struct Cubemap {
     image_handle: Handle<Image>,
 }
 
-#[derive(Debug, Default, Clone, Eq, PartialEq, Hash, States)]
-enum AppState {
-    Generated,
-    #[default]
-    Idle,
-}
-
 fn main() {
     App::new()
         .add_plugins((
@@ -35,22 +41,61 @@ fn main() {
                 }),
                 ..Default::default()
             }),
-            RapierPhysicsPlugin::<NoUserData>::default(),
+            // Step physics inside `GgrsSchedule` (NOT `.in_fixed_schedule()`,
+            // which is sugar for `.in_schedule(FixedUpdate)` and would
+            // overwrite this back to `FixedUpdate`) so replaying rolled-back
+            // frames re-runs the exact same physics step; the fixed `dt`
+            // comes from the `RapierConfiguration::timestep_mode` below.
+            RapierPhysicsPlugin::<NoUserData>::default().in_schedule(GgrsSchedule),
+            NetcodePlugin,
         ))
         .insert_resource(Msaa::default())
-        .add_systems(Startup, setup_world)
+        .insert_resource(SkyConfig::default())
+        .insert_resource(RenderConfig::default())
+        .insert_resource(CameraController::default())
+        .insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed {
+                dt: 1. / 60.,
+                substeps: 1,
+            },
+            ..Default::default()
+        })
+        .add_systems(
+            Startup,
+            (
+                configure_match,
+                setup_world.after(configure_match),
+                sky::setup_sky.after(setup_world),
+                audio::setup_audio,
+                camera::setup_camera_controller,
+                start_session.after(setup_world),
+            ),
+        )
+        .add_systems(ReadInputs, read_local_input)
+        .add_systems(
+            GgrsSchedule,
+            (
+                apply_rollback_input,
+                check_track_progress,
+                generate_track,
+                despawn_stale_chunks,
+            )
+                .chain(),
+        )
         .add_systems(
             Update,
             (
                 correct_skybox,
-                follow_player,
+                update_sky,
                 handle_input,
-                generate_floor.run_if(in_state(AppState::Idle)),
-                check_distance,
+                mouse_look,
+                orbit_camera.after(mouse_look),
+                update_rolling_audio,
+                play_impact_sfx,
+                apply_music_mute,
             ),
         )
         .add_event::<GenerateMapEvent>()
-        .add_state::<AppState>()
         .run();
 }
 
@@ -61,6 +106,9 @@ fn setup_world(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut ev_generatemap: EventWriter<GenerateMapEvent>,
+    sky_config: Res<SkyConfig>,
+    render_config: Res<RenderConfig>,
+    match_config: Res<MatchConfig>,
 ) {
     // Load the skybox.
     let skybox_handle = asset_server.load("skybox.png");
@@ -70,64 +118,90 @@ fn setup_world(
         image_handle: skybox_handle.clone(),
     });
 
-    // Spawns the camera and skybox.
-    commands.spawn((Camera3dBundle::default(), Skybox(skybox_handle.clone())));
-
-    // Spawn the player as a ball.
-    commands
-        .spawn(RigidBody::Dynamic)
-        .insert(Collider::ball(0.5))
-        .insert(Restitution::coefficient(0.7))
-        .insert(Damping {
-            linear_damping: 0.5,
-            angular_damping: 1.0,
-        })
-        .insert(GravityScale(10.0))
-        .insert(Velocity::zero())
-        .insert(PbrBundle {
-            mesh: meshes
-                .add(Mesh::from(shape::UVSphere {
-                    radius: 0.5,
-                    sectors: 32,
-                    stacks: 32,
-                }))
-                .into(),
-            material: materials
-                .add(StandardMaterial {
-                    base_color: Color::hex("FF0000").unwrap(),
-                    perceptual_roughness: 0.,
-                    ..default()
-                })
-                .into(),
-            ..default()
-        })
-        .insert(TransformBundle::from(Transform::from_xyz(0.0, 4.0, 0.0)))
-        .insert(Player);
+    // Spawns the HDR camera (with bloom so the marble's specular highlights
+    // glow) and the skybox.
+    let (camera, bloom, exposure) = render::camera_bundle(&render_config);
+    commands.spawn((camera, bloom, exposure, Skybox(skybox_handle.clone())));
+
+    // Spawn one ball per player in the match, spaced out on X so they don't
+    // all start stacked on top of each other.
+    let ball_mesh = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 0.5,
+        sectors: 32,
+        stacks: 32,
+    }));
+
+    for player_index in 0..match_config.num_players {
+        let mut ball = commands.spawn(RigidBody::Dynamic);
+        ball.insert(Collider::ball(0.5))
+            .insert(ActiveEvents::COLLISION_EVENTS)
+            .insert(Restitution::coefficient(0.7))
+            .insert(Damping {
+                linear_damping: 0.5,
+                angular_damping: 1.0,
+            })
+            .insert(GravityScale(10.0))
+            .insert(Velocity::zero())
+            .insert(PbrBundle {
+                mesh: ball_mesh.clone().into(),
+                material: materials
+                    .add(StandardMaterial {
+                        base_color: Color::hex("FF0000").unwrap(),
+                        perceptual_roughness: 0.,
+                        ..default()
+                    })
+                    .into(),
+                ..default()
+            })
+            .insert(TransformBundle::from(Transform::from_xyz(
+                player_index as f32 * 3.0,
+                4.0,
+                0.0,
+            )))
+            .insert(PlayerHandle(player_index))
+            .insert(RollbackState::default())
+            .add_rollback();
 
-    // Spawn a light that acts as sunlight.
+        if player_index == match_config.local_player_index {
+            ball.insert(LocalPlayer);
+        }
+    }
+
+    // Spawn a light that acts as sunlight, tracking the same sun direction as
+    // the procedural sky so shadows and sky stay consistent. Cascaded
+    // shadows keep shadows crisp near the player while still covering the
+    // long, receding track.
     commands.spawn(DirectionalLightBundle {
         directional_light: DirectionalLight {
             illuminance: 10000.,
             shadows_enabled: true,
             ..Default::default()
         },
-        transform: Transform {
-            rotation: Quat::from_scaled_axis(Vec3::new(-PI / 2., 0., 0.)),
-            ..default()
-        },
+        transform: Transform::from_translation(Vec3::ZERO)
+            .looking_to(-sky_config.sun_direction(), Vec3::Y),
+        cascade_shadow_config: render::track_shadow_config(),
         ..Default::default()
     });
 
+    commands.insert_resource(TrackState::new(match_config.seed, Vec3::new(0.0, 2.0, 0.0)));
     ev_generatemap.send(GenerateMapEvent);
 }
 
-/// Converts the input skybox to a cubemap.
+/// Converts the static fallback skybox to a cubemap, when `SkyConfig` selects
+/// it, applying `RenderConfig::skybox_brightness` so it can be balanced
+/// against the HDR camera's exposure.
 fn correct_skybox(
     asset_server: Res<AssetServer>,
     mut images: ResMut<Assets<Image>>,
     mut cubemap: ResMut<Cubemap>,
     mut skyboxes: Query<&mut Skybox>,
+    sky_config: Res<SkyConfig>,
+    render_config: Res<RenderConfig>,
 ) {
+    if sky_config.mode != SkyMode::Cubemap {
+        return;
+    }
+
     if !cubemap.is_loaded
         && asset_server.get_load_state(cubemap.image_handle.clone_weak()) == Some(LoadState::Loaded)
     {
@@ -142,6 +216,13 @@ fn correct_skybox(
             });
         }
 
+        for rgba in image.data.chunks_exact_mut(4) {
+            for channel in &mut rgba[..3] {
+                *channel = (*channel as f32 * render_config.skybox_brightness / 1000.0)
+                    .clamp(0.0, 255.0) as u8;
+            }
+        }
+
         for mut skybox in &mut skyboxes {
             skybox.0 = cubemap.image_handle.clone();
         }
@@ -150,104 +231,34 @@ fn correct_skybox(
     }
 }
 
-/// Locks the camera to the position of the player.
-fn follow_player(
-    mut camera: Query<&mut Transform, With<Camera>>,
-    player: Query<&Transform, (With<Player>, Without<Camera>)>,
-) {
-    let mut camera = camera.get_single_mut().unwrap();
-    let player = player.get_single().unwrap();
-
-    // Lock the position of the camera to the player
-    camera.translation.x = player.translation.x + 0.;
-    camera.translation.y = player.translation.y + 5.;
-    camera.translation.z = player.translation.z + 10.;
-
-    // Rotate the camera to look at the ball
-    *camera = camera.looking_at(player.translation, Vec3::Y);
-}
-
-/// A handler for user input.
+/// A handler for user input not part of the rollback simulation (window
+/// chrome and music muting only; steering input is read by
+/// `netcode::read_local_input` and applied deterministically inside
+/// `GgrsSchedule`).
 fn handle_input(
-    mut player: Query<&mut Velocity, With<Player>>,
     mut windows: Query<&mut Window>,
+    mut music_muted: ResMut<MusicMuted>,
+    mut camera_controller: ResMut<CameraController>,
     keyboard_input: Res<Input<KeyCode>>,
 ) {
-    let mut player = player.get_single_mut().unwrap();
     let mut window = windows.single_mut();
 
-    if keyboard_input.pressed(KeyCode::A) {
-        player.linvel.x -= 0.1;
-    };
-
-    if keyboard_input.pressed(KeyCode::S) {
-        player.linvel.x += 0.1;
-    };
-
     if keyboard_input.just_pressed(KeyCode::F11) {
         window.mode = match window.mode {
             WindowMode::BorderlessFullscreen => WindowMode::Windowed,
             _ => WindowMode::BorderlessFullscreen,
         }
     };
-}
 
-/// Generates the floor that the player will roll down.
-fn generate_floor(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut ev_generatemap: EventReader<GenerateMapEvent>,
-    player: Query<&Transform, (With<Player>, Without<Camera>)>,
-) {
-    for _ev in ev_generatemap.read() {
-        let translation = player.single().translation;
-        commands
-            .spawn(Collider::cuboid(5.0, 0.5, 25.0))
-            .insert(PbrBundle {
-                mesh: meshes
-                    .add(Mesh::from(shape::Box {
-                        min_x: -5.,
-                        max_x: 5.,
-                        min_y: -0.5,
-                        max_y: 0.5,
-                        min_z: -25.,
-                        max_z: 25.,
-                    }))
-                    .into(),
-                material: materials
-                    .add(StandardMaterial {
-                        base_color: Color::hex("FFFFFF").unwrap(),
-                        perceptual_roughness: 1.,
-                        ..default()
-                    })
-                    .into(),
-                ..default()
-            })
-            .insert(TransformBundle::from(
-                Transform::from_xyz(translation.x, translation.y - 2.0, translation.z)
-                    .with_rotation(Quat::from_rotation_x(-PI / 8.)),
-            ));
-    }
+    if keyboard_input.just_pressed(KeyCode::M) {
+        music_muted.0 = !music_muted.0;
+    };
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        camera_controller.cursor_grabbed = !camera_controller.cursor_grabbed;
+        camera::apply_cursor_grab(&mut window, camera_controller.cursor_grabbed);
+    };
 }
 
 #[derive(Event)]
-struct GenerateMapEvent;
-
-fn check_distance(
-    mut ev_generatemap: EventWriter<GenerateMapEvent>,
-    query: Query<&Transform, (With<Player>, Without<Camera>)>,
-    mut next_state: ResMut<NextState<AppState>>,
-) {
-    for transform in query.iter() {
-        match transform.translation.z.ceil().abs() % 10. == 0. {
-            true => {
-                ev_generatemap.send(GenerateMapEvent);
-                next_state.set(AppState::Generated);
-            }
-            false => {
-                next_state.set(AppState::Idle);
-            }
-        }
-    }
-}
+pub(crate) struct GenerateMapEvent;
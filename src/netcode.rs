@@ -0,0 +1,219 @@
+use std::net::SocketAddr;
+
+use bevy::prelude::*;
+use bevy_ggrs::{ggrs, GgrsApp, GgrsPlugin, GgrsSchedule, PlayerInputs, Rollback, Session};
+use bevy_rapier3d::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+use crate::track::TrackState;
+
+/// Bitflags packed into a single byte so GGRS can diff/serialize inputs cheaply.
+const INPUT_LEFT: u8 = 1 << 0;
+const INPUT_RIGHT: u8 = 1 << 1;
+
+/// The input GGRS rolls back and replays each tick. Mirrors the A/S keys that
+/// `handle_input` used to apply directly to `Velocity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable, Default)]
+#[repr(C)]
+pub struct RollbackInput {
+    pub buttons: u8,
+}
+
+/// The GGRS config for this game: packed button input, socket addresses for
+/// peers, and a trivial checksum-able state marker.
+#[derive(Debug)]
+pub struct GgrsConfig;
+
+impl ggrs::Config for GgrsConfig {
+    type Input = RollbackInput;
+    type State = u8;
+    type Address = SocketAddr;
+}
+
+/// Shared match setup that must be identical on every peer: the seed that
+/// feeds all RNG-driven map generation, how many balls to spawn, and which
+/// one this peer controls.
+#[derive(Resource, Clone, Copy)]
+pub struct MatchConfig {
+    pub seed: u64,
+    pub input_delay: usize,
+    pub max_prediction_window: usize,
+    pub num_players: usize,
+    pub local_player_index: usize,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            input_delay: 2,
+            max_prediction_window: 10,
+            num_players: 1,
+            local_player_index: 0,
+        }
+    }
+}
+
+/// Which GGRS player index a ball belongs to, so `apply_rollback_input` can
+/// drive each player's ball from only that player's input slot.
+#[derive(Component)]
+pub struct PlayerHandle(pub usize);
+
+/// Marks the one ball this peer's camera/track-generation/audio follow.
+/// Every peer has all players' balls simulated, but each peer only has one
+/// `LocalPlayer`.
+#[derive(Component)]
+pub struct LocalPlayer;
+
+/// Tracks an entity's rollback-synchronized progress along the track (the
+/// "z-progress" `check_track_progress` writes for the local player).
+#[derive(Component, Default, Clone, Reflect)]
+#[reflect(Component)]
+pub struct RollbackState {
+    pub z_progress: f32,
+}
+
+/// Registers the GGRS plugin, the fixed 60Hz rollback schedule, and the
+/// components/resources the rollback snapshot is built from.
+pub struct NetcodePlugin;
+
+impl Plugin for NetcodePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(GgrsPlugin::<GgrsConfig>::default())
+            .init_resource::<MatchConfig>()
+            .rollback_component_with_clone::<Transform>()
+            .rollback_component_with_clone::<Velocity>()
+            .rollback_component_with_clone::<RollbackState>()
+            .rollback_resource_with_clone::<TrackState>()
+            .set_rollback_schedule_fps(60);
+    }
+}
+
+/// Reads the local keyboard into the packed `RollbackInput` GGRS expects
+/// each tick; this replaces the direct `Velocity` mutation `handle_input`
+/// used to perform locally.
+pub fn read_local_input(keyboard_input: Res<Input<KeyCode>>) -> RollbackInput {
+    let mut buttons = 0u8;
+
+    if keyboard_input.pressed(KeyCode::A) {
+        buttons |= INPUT_LEFT;
+    }
+    if keyboard_input.pressed(KeyCode::S) {
+        buttons |= INPUT_RIGHT;
+    }
+
+    RollbackInput { buttons }
+}
+
+/// Applies each confirmed (possibly replayed) tick's input to the ball it
+/// belongs to: every player's input drives only their own `PlayerHandle`
+/// ball, so a 2-player race doesn't turn into one marble steered by both
+/// peers at once.
+pub fn apply_rollback_input(
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    mut players: Query<(&mut Velocity, &PlayerHandle), With<Rollback>>,
+) {
+    for (mut velocity, handle) in &mut players {
+        let (input, _status) = inputs[handle.0];
+
+        if input.buttons & INPUT_LEFT != 0 {
+            velocity.linvel.x -= 0.1;
+        }
+        if input.buttons & INPUT_RIGHT != 0 {
+            velocity.linvel.x += 0.1;
+        }
+    }
+}
+
+fn cli_arg<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+/// Reads `--local <addr> --remote <addr> --player <0|1>` off the command
+/// line so `setup_world` knows how many balls to spawn and which one is
+/// local *before* the real GGRS session is built in `start_session`.
+pub fn configure_match(mut match_config: ResMut<MatchConfig>) {
+    let args: Vec<String> = std::env::args().collect();
+
+    if let (Some(_), Some(_), Some(player_index)) = (
+        cli_arg(&args, "--local"),
+        cli_arg(&args, "--remote"),
+        cli_arg(&args, "--player"),
+    ) {
+        match_config.num_players = 2;
+        match_config.local_player_index = player_index.parse().expect("--player must be 0 or 1");
+    }
+}
+
+/// Starts the GGRS session the rollback schedule needs to run at all:
+/// `--local`/`--remote`/`--player` on the command line starts a real
+/// two-peer race, otherwise this falls back to a local synctest session so
+/// the game (and the rollback schedule) still runs out of the box.
+pub fn start_session(mut commands: Commands, match_config: Res<MatchConfig>) {
+    let args: Vec<String> = std::env::args().collect();
+
+    let session = match (cli_arg(&args, "--local"), cli_arg(&args, "--remote")) {
+        (Some(local), Some(remote)) => {
+            let local_addr: SocketAddr = local.parse().expect("invalid --local address");
+            let remote_addr: SocketAddr = remote.parse().expect("invalid --remote address");
+            Session::P2P(build_p2p_session(
+                local_addr,
+                remote_addr,
+                match_config.local_player_index,
+                &match_config,
+            ))
+        }
+        _ => Session::SyncTest(build_synctest_session(match_config.num_players)),
+    };
+
+    commands.insert_resource(session);
+}
+
+/// Builds a local "synctest" session that every frame rolls back and
+/// re-simulates the last 7 frames, comparing checksums, to catch
+/// non-determinism before it ever reaches a real peer.
+fn build_synctest_session(num_players: usize) -> ggrs::SyncTestSession<GgrsConfig> {
+    ggrs::SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(num_players)
+        .with_check_distance(7)
+        .start_synctest_session()
+        .expect("failed to start synctest session")
+}
+
+/// Builds a two-peer UDP rollback session with the prediction window and
+/// input delay tuned for a marble race: generous enough to hide typical
+/// internet jitter, tight enough to keep inputs feeling responsive.
+fn build_p2p_session(
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    local_player_index: usize,
+    config: &MatchConfig,
+) -> ggrs::P2PSession<GgrsConfig> {
+    let socket = bevy_ggrs::UdpNonBlockingSocket::bind_to_port(local_addr.port())
+        .expect("failed to bind rollback socket");
+
+    let mut builder = ggrs::SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(2)
+        .with_max_prediction_window(config.max_prediction_window)
+        .expect("invalid prediction window")
+        .with_input_delay(config.input_delay);
+
+    for player_index in 0..2 {
+        builder = if player_index == local_player_index {
+            builder
+                .add_player(ggrs::PlayerType::Local, player_index)
+                .expect("failed to add local player")
+        } else {
+            builder
+                .add_player(ggrs::PlayerType::Remote(remote_addr), player_index)
+                .expect("failed to add remote player")
+        };
+    }
+
+    builder
+        .start_p2p_session(socket)
+        .expect("failed to start p2p session")
+}
@@ -0,0 +1,68 @@
+use bevy::{
+    core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
+    pbr::CascadeShadowConfigBuilder,
+    prelude::*,
+    render::camera::Exposure,
+};
+
+/// Exposure/bloom/skybox knobs for the HDR render path, so the look can be
+/// tuned without touching `setup_world`.
+#[derive(Resource)]
+pub struct RenderConfig {
+    /// Camera exposure, in EV100 (passed straight to the camera's `Exposure`
+    /// component).
+    pub exposure: f32,
+    pub bloom_intensity: f32,
+    /// Scales the fallback cubemap's pixel colors by `skybox_brightness /
+    /// 1000` before it's assigned (1000 leaves them unchanged), so the sky
+    /// can be balanced against the HDR exposure above. The procedural sky is
+    /// not scaled.
+    pub skybox_brightness: f32,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            bloom_intensity: 0.15,
+            skybox_brightness: 1000.0,
+        }
+    }
+}
+
+/// The `Camera3dBundle` components this game always wants: HDR enabled (so
+/// bloom has something to work with) and tonemapping that doesn't crush the
+/// marble's specular highlights.
+pub fn camera_bundle(render_config: &RenderConfig) -> (Camera3dBundle, BloomSettings, Exposure) {
+    (
+        Camera3dBundle {
+            camera: Camera {
+                hdr: true,
+                ..default()
+            },
+            tonemapping: Tonemapping::TonyMcMapface,
+            ..default()
+        },
+        BloomSettings {
+            intensity: render_config.bloom_intensity,
+            ..default()
+        },
+        Exposure {
+            ev100: render_config.exposure,
+        },
+    )
+}
+
+/// Builds a cascaded shadow map tuned for a long, receding track: tight
+/// cascades close to the player for crisp contact shadows, looser ones
+/// further out so distant chunks still get covered.
+pub fn track_shadow_config() -> bevy::pbr::CascadeShadowConfig {
+    CascadeShadowConfigBuilder {
+        num_cascades: 4,
+        minimum_distance: 0.3,
+        maximum_distance: 150.0,
+        first_cascade_far_bound: 10.0,
+        overlap_proportion: 0.2,
+    }
+    .build()
+}
@@ -0,0 +1,249 @@
+use bevy::{
+    core_pipeline::Skybox,
+    prelude::*,
+    render::render_resource::{
+        Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+    },
+};
+
+/// Which source the sky is currently rendered from.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum SkyMode {
+    /// A procedurally generated Preetham-style scattering sky.
+    #[default]
+    Procedural,
+    /// The original static cubemap, kept as a fallback.
+    Cubemap,
+}
+
+/// Parameters driving the procedural sky's sun position and scattering look.
+#[derive(Resource)]
+pub struct SkyConfig {
+    pub mode: SkyMode,
+    /// Sun elevation above the horizon, in radians.
+    pub sun_elevation: f32,
+    /// Sun azimuth, in radians, measured around the Y axis.
+    pub sun_azimuth: f32,
+    /// Haziness of the atmosphere; higher values wash out the horizon.
+    pub turbidity: f32,
+    /// Rayleigh scattering coefficients for the R/G/B wavelengths.
+    pub rayleigh_coefficients: Vec3,
+    /// Whether the sun should advance automatically over time.
+    pub animate_time_of_day: bool,
+    /// Radians per second the sun advances when `animate_time_of_day` is set.
+    pub time_of_day_speed: f32,
+}
+
+impl Default for SkyConfig {
+    fn default() -> Self {
+        Self {
+            mode: SkyMode::Procedural,
+            sun_elevation: 0.9,
+            sun_azimuth: 0.0,
+            turbidity: 3.0,
+            rayleigh_coefficients: Vec3::new(5.5, 13.0, 22.4),
+            animate_time_of_day: false,
+            time_of_day_speed: 0.05,
+        }
+    }
+}
+
+impl SkyConfig {
+    /// The unit direction pointing *toward* the sun, in world space (light
+    /// travels along its negation).
+    pub fn sun_direction(&self) -> Vec3 {
+        let (sin_el, cos_el) = self.sun_elevation.sin_cos();
+        let (sin_az, cos_az) = self.sun_azimuth.sin_cos();
+        Vec3::new(cos_el * sin_az, sin_el, cos_el * cos_az).normalize()
+    }
+}
+
+/// The dynamic sky texture swapped into each `Skybox` component.
+#[derive(Resource)]
+pub struct ProceduralSky {
+    pub image_handle: Handle<Image>,
+}
+
+const FACE_SIZE: u32 = 64;
+
+/// The 6 cubemap face view directions, in the order Bevy expects for an array texture.
+const FACE_DIRECTIONS: [Vec3; 6] = [
+    Vec3::X,
+    Vec3::NEG_X,
+    Vec3::Y,
+    Vec3::NEG_Y,
+    Vec3::Z,
+    Vec3::NEG_Z,
+];
+
+/// Preetham sky luminance model. `theta` is the angle between the view and sun
+/// directions, `gamma` is the view's elevation angle above the horizon.
+fn preetham_color(theta: f32, gamma: f32, turbidity: f32, coefficients: Vec3) -> Vec3 {
+    let a = -1.0;
+    let b = -0.32;
+    let c = 10.0 + turbidity;
+    let d = -3.0;
+    let e = 0.45;
+
+    let zenith_luminance = 0.15 * turbidity + 1.0;
+
+    // Guard the real singularity (cos(gamma) -> 0 as gamma -> +/-FRAC_PI_2,
+    // i.e. straight up/down), not the horizon (gamma == 0), which is exactly
+    // the region the Preetham gradient needs to stay accurate in.
+    let cos_gamma = gamma.cos().max(0.01);
+    let distribution =
+        (1.0 + a * (b / cos_gamma).exp()) * (1.0 + c * (d * theta).exp() + e * theta.cos().powi(2));
+
+    coefficients * zenith_luminance * distribution.max(0.0)
+}
+
+fn face_data(view_dir: Vec3, sun_dir: Vec3, config: &SkyConfig) -> Vec<u8> {
+    let mut data = Vec::with_capacity((FACE_SIZE * FACE_SIZE * 4) as usize);
+
+    let right = if view_dir.abs_diff_eq(Vec3::Y, 1e-3) || view_dir.abs_diff_eq(Vec3::NEG_Y, 1e-3) {
+        Vec3::X
+    } else {
+        view_dir.cross(Vec3::Y).normalize()
+    };
+    let up = right.cross(view_dir).normalize();
+
+    for y in 0..FACE_SIZE {
+        for x in 0..FACE_SIZE {
+            let u = (x as f32 + 0.5) / FACE_SIZE as f32 * 2.0 - 1.0;
+            let v = (y as f32 + 0.5) / FACE_SIZE as f32 * 2.0 - 1.0;
+            let sample_dir = (view_dir + right * u + up * v).normalize();
+
+            let theta = sample_dir.dot(sun_dir).clamp(-1.0, 1.0).acos();
+            let gamma = sample_dir.y.asin();
+
+            let color =
+                preetham_color(theta, gamma, config.turbidity, config.rayleigh_coefficients) / 30.0;
+
+            data.push((color.x.clamp(0.0, 1.0) * 255.0) as u8);
+            data.push((color.y.clamp(0.0, 1.0) * 255.0) as u8);
+            data.push((color.z.clamp(0.0, 1.0) * 255.0) as u8);
+            data.push(255);
+        }
+    }
+
+    data
+}
+
+/// Bakes the current `SkyConfig` sun direction into a fresh cubemap array image.
+fn bake_sky_image(config: &SkyConfig) -> Image {
+    let sun_dir = config.sun_direction();
+    let mut data = Vec::with_capacity((FACE_SIZE * FACE_SIZE * 4 * 6) as usize);
+    for face_dir in FACE_DIRECTIONS {
+        data.extend(face_data(face_dir, sun_dir, config));
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: FACE_SIZE,
+            height: FACE_SIZE * 6,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.reinterpret_stacked_2d_as_array(6);
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..default()
+    });
+    image
+}
+
+/// Bakes the initial procedural sky image from `SkyConfig`, plugs it into
+/// every `Skybox` component, and stores its handle as `ProceduralSky`.
+pub fn setup_sky(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<SkyConfig>,
+    mut skyboxes: Query<&mut Skybox>,
+) {
+    let image_handle = images.add(bake_sky_image(&config));
+
+    for mut skybox in &mut skyboxes {
+        skybox.0 = image_handle.clone();
+    }
+
+    commands.insert_resource(ProceduralSky { image_handle });
+}
+
+/// Recomputes the sky color whenever the sun moves, and keeps the
+/// `DirectionalLight` pointed at the same sun direction so shadows agree
+/// with what's painted on the skybox.
+pub fn update_sky(
+    time: Res<Time>,
+    mut config: ResMut<SkyConfig>,
+    mut images: ResMut<Assets<Image>>,
+    sky: Res<ProceduralSky>,
+    mut skyboxes: Query<&mut Skybox>,
+    mut sun: Query<&mut Transform, With<DirectionalLight>>,
+) {
+    if config.mode != SkyMode::Procedural {
+        return;
+    }
+
+    if config.animate_time_of_day {
+        config.sun_azimuth += config.time_of_day_speed * time.delta_seconds();
+    } else if !config.is_changed() {
+        return;
+    }
+
+    if let Some(image) = images.get_mut(&sky.image_handle) {
+        *image = bake_sky_image(&config);
+    }
+
+    let sun_dir = config.sun_direction();
+    for mut transform in &mut sun {
+        transform.look_to(-sun_dir, Vec3::Y);
+    }
+
+    for mut skybox in &mut skyboxes {
+        skybox.0 = sky.image_handle.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn sun_direction_at_horizon_is_level() {
+        let config = SkyConfig {
+            sun_elevation: 0.0,
+            sun_azimuth: 0.0,
+            ..SkyConfig::default()
+        };
+        assert!(config.sun_direction().y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn sun_direction_at_zenith_points_straight_up() {
+        let config = SkyConfig {
+            sun_elevation: FRAC_PI_2,
+            sun_azimuth: 0.0,
+            ..SkyConfig::default()
+        };
+        assert!((config.sun_direction().y - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn preetham_color_stays_finite_near_straight_up() {
+        let coefficients = Vec3::new(5.5, 13.0, 22.4);
+        let color = preetham_color(0.0, FRAC_PI_2 - 1e-4, 3.0, coefficients);
+        assert!(color.is_finite());
+    }
+
+    #[test]
+    fn preetham_color_stays_finite_at_the_horizon() {
+        let coefficients = Vec3::new(5.5, 13.0, 22.4);
+        let color = preetham_color(0.0, 0.0, 3.0, coefficients);
+        assert!(color.is_finite());
+    }
+}
@@ -0,0 +1,312 @@
+use bevy::prelude::*;
+use bevy_ggrs::AddRollbackCommandExtension;
+use bevy_rapier3d::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    netcode::{LocalPlayer, RollbackState},
+    GenerateMapEvent,
+};
+
+/// Length, in world units, of each straight-line step along a chunk's
+/// spline. Colliders/mesh quads are emitted at this resolution.
+const STEP_LENGTH: f32 = 5.0;
+/// How many steps make up one chunk between control points.
+const STEPS_PER_CHUNK: u32 = 10;
+/// Track half-width, the same for every piece of every chunk.
+const HALF_WIDTH: f32 = 5.0;
+const HALF_HEIGHT: f32 = 0.5;
+/// How many chunks behind the most recently emitted one are kept alive
+/// before despawning.
+const CHUNKS_BEHIND_TO_KEEP: u32 = 3;
+
+/// A single quad-strip piece of track, tagged with the chunk it belongs to
+/// so `despawn_stale_chunks` can drop whole chunks at once.
+#[derive(Component)]
+struct TrackPiece {
+    chunk_index: u32,
+}
+
+/// Tracks where the previously emitted chunk ended, so the next chunk's
+/// spline starts exactly at that position and tangent (C0/C1 continuity
+/// across the seam).
+#[derive(Resource, Clone)]
+pub struct TrackState {
+    rng: StdRng,
+    exit_position: Vec3,
+    exit_tangent: Vec3,
+    next_chunk_index: u32,
+}
+
+impl TrackState {
+    pub fn new(seed: u64, start_position: Vec3) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            exit_position: start_position,
+            exit_tangent: -Vec3::Z,
+            next_chunk_index: 0,
+        }
+    }
+}
+
+/// What kind of segment a chunk is, chosen by the seeded RNG so every peer
+/// (and every replayed rollback frame) agrees on the track shape.
+#[derive(Clone, Copy)]
+enum ChunkKind {
+    Slope,
+    Gap,
+    Ramp,
+    Obstacle,
+}
+
+fn sample_chunk_kind(rng: &mut StdRng) -> ChunkKind {
+    match rng.gen_range(0..10) {
+        0 => ChunkKind::Gap,
+        1 => ChunkKind::Ramp,
+        2 => ChunkKind::Obstacle,
+        _ => ChunkKind::Slope,
+    }
+}
+
+/// How many of a `Ramp` chunk's final steps curl upward into a launch lip,
+/// and how steep that lip gets at its tip.
+const RAMP_LAUNCH_STEPS: u32 = 3;
+const RAMP_LAUNCH_ANGLE: f32 = 0.6;
+
+/// Size of the standing block an `Obstacle` chunk drops into the middle of
+/// its lane, and how far off-center (within track width) it can land.
+const OBSTACLE_HALF_SIZE: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+const OBSTACLE_MAX_OFFSET: f32 = HALF_WIDTH - OBSTACLE_HALF_SIZE.x;
+
+/// Cubic Bezier position at `t` in `[0, 1]` for control points `p0..p3`.
+fn bezier_point(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let u = 1.0 - t;
+    p0 * u.powi(3) + p1 * 3.0 * u.powi(2) * t + p2 * 3.0 * u * t.powi(2) + p3 * t.powi(3)
+}
+
+/// Cubic Bezier tangent (unnormalized derivative) at `t`.
+fn bezier_tangent(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let u = 1.0 - t;
+    3.0 * u.powi(2) * (p1 - p0) + 6.0 * u * t * (p2 - p1) + 3.0 * t.powi(2) * (p3 - p2)
+}
+
+/// Spawns the next chunk's worth of track, sampling pitch/yaw from the
+/// seeded RNG and stepping along a cubic Bezier spline so it joins smoothly
+/// with the previous chunk's exit position and tangent. The width is not
+/// sampled: every chunk is `2 * HALF_WIDTH` wide.
+pub fn generate_track(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut ev_generatemap: EventReader<GenerateMapEvent>,
+    mut track: ResMut<TrackState>,
+) {
+    for _ev in ev_generatemap.read() {
+        let chunk_index = track.next_chunk_index;
+        track.next_chunk_index += 1;
+
+        let kind = sample_chunk_kind(&mut track.rng);
+        let pitch: f32 = track.rng.gen_range(-0.5_f32..-0.1);
+        let yaw: f32 = track.rng.gen_range(-0.4_f32..0.4);
+        let chunk_length = STEP_LENGTH * STEPS_PER_CHUNK as f32;
+
+        let p0 = track.exit_position;
+        let entry_tangent = track.exit_tangent;
+
+        let heading = Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch);
+        let exit_tangent = (heading * entry_tangent).normalize();
+        let p3 = p0 + exit_tangent * chunk_length;
+
+        // Control points pulled out along each end's tangent, so the curve's
+        // derivative at t=0 matches the incoming seam and at t=1 matches the
+        // outgoing one.
+        let p1 = p0 + entry_tangent * (chunk_length / 3.0);
+        let p2 = p3 - exit_tangent * (chunk_length / 3.0);
+
+        if matches!(kind, ChunkKind::Gap) {
+            // Leave this chunk's span empty; the ball is expected to fly
+            // across it (or fall, if it misses the next chunk's entry).
+        } else {
+            let step_material = materials.add(StandardMaterial {
+                base_color: Color::hex("FFFFFF").unwrap(),
+                perceptual_roughness: 1.,
+                ..default()
+            });
+
+            let obstacle_material = matches!(kind, ChunkKind::Obstacle).then(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::hex("CC2222").unwrap(),
+                    perceptual_roughness: 0.8,
+                    ..default()
+                })
+            });
+            let obstacle_step = STEPS_PER_CHUNK / 2;
+            let obstacle_offset = track
+                .rng
+                .gen_range(-OBSTACLE_MAX_OFFSET..OBSTACLE_MAX_OFFSET);
+
+            for step in 0..STEPS_PER_CHUNK {
+                let t0 = step as f32 / STEPS_PER_CHUNK as f32;
+                let t1 = (step + 1) as f32 / STEPS_PER_CHUNK as f32;
+
+                let start = bezier_point(p0, p1, p2, p3, t0);
+                let end = bezier_point(p0, p1, p2, p3, t1);
+                let mid = (start + end) / 2.0;
+                let tangent = bezier_tangent(p0, p1, p2, p3, (t0 + t1) / 2.0).normalize();
+
+                // The last few steps of a `Ramp` chunk curl upward into a
+                // launch lip instead of following the slope's usual downward
+                // pitch, so hitting one actually sends the ball airborne.
+                let launch_progress = if matches!(kind, ChunkKind::Ramp) {
+                    (step + RAMP_LAUNCH_STEPS).saturating_sub(STEPS_PER_CHUNK - 1)
+                } else {
+                    0
+                };
+                let launch_angle =
+                    (launch_progress as f32 / RAMP_LAUNCH_STEPS as f32) * RAMP_LAUNCH_ANGLE;
+
+                let half_length = (end - start).length() / 2.0;
+                // Local +Z along the tangent with local +Y kept near world-up,
+                // so the lip and obstacles sit on top of the surface.
+                let rotation = Transform::IDENTITY.looking_to(-tangent, Vec3::Y).rotation
+                    * Quat::from_rotation_x(-launch_angle);
+
+                commands
+                    .spawn(Collider::cuboid(HALF_WIDTH, HALF_HEIGHT, half_length))
+                    .insert(ActiveEvents::COLLISION_EVENTS)
+                    .insert(PbrBundle {
+                        mesh: meshes
+                            .add(Mesh::from(shape::Box {
+                                min_x: -HALF_WIDTH,
+                                max_x: HALF_WIDTH,
+                                min_y: -HALF_HEIGHT,
+                                max_y: HALF_HEIGHT,
+                                min_z: -half_length,
+                                max_z: half_length,
+                            }))
+                            .into(),
+                        material: step_material.clone(),
+                        ..default()
+                    })
+                    .insert(TransformBundle::from(
+                        Transform::from_translation(mid).with_rotation(rotation),
+                    ))
+                    .insert(TrackPiece { chunk_index })
+                    .add_rollback();
+
+                if let (Some(obstacle_material), true) = (&obstacle_material, step == obstacle_step)
+                {
+                    let obstacle_position = mid
+                        + rotation
+                            * Vec3::new(obstacle_offset, HALF_HEIGHT + OBSTACLE_HALF_SIZE.y, 0.0);
+
+                    commands
+                        .spawn(Collider::cuboid(
+                            OBSTACLE_HALF_SIZE.x,
+                            OBSTACLE_HALF_SIZE.y,
+                            OBSTACLE_HALF_SIZE.z,
+                        ))
+                        .insert(ActiveEvents::COLLISION_EVENTS)
+                        .insert(PbrBundle {
+                            mesh: meshes
+                                .add(Mesh::from(shape::Box::new(
+                                    OBSTACLE_HALF_SIZE.x * 2.0,
+                                    OBSTACLE_HALF_SIZE.y * 2.0,
+                                    OBSTACLE_HALF_SIZE.z * 2.0,
+                                )))
+                                .into(),
+                            material: obstacle_material.clone(),
+                            ..default()
+                        })
+                        .insert(TransformBundle::from(
+                            Transform::from_translation(obstacle_position).with_rotation(rotation),
+                        ))
+                        .insert(TrackPiece { chunk_index })
+                        .add_rollback();
+                }
+            }
+        }
+
+        track.exit_position = p3;
+        track.exit_tangent = exit_tangent;
+    }
+}
+
+/// Despawns any chunk more than `CHUNKS_BEHIND_TO_KEEP` behind the most
+/// recently emitted one, so the world stays bounded instead of growing
+/// forever.
+pub fn despawn_stale_chunks(
+    mut commands: Commands,
+    track: Res<TrackState>,
+    pieces: Query<(Entity, &TrackPiece)>,
+) {
+    let Some(newest) = track.next_chunk_index.checked_sub(1) else {
+        return;
+    };
+
+    for (entity, piece) in &pieces {
+        if newest.saturating_sub(piece.chunk_index) > CHUNKS_BEHIND_TO_KEEP {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Fires `GenerateMapEvent` once the player crosses into the next chunk's
+/// span, using the actual spawned chunk boundary rather than a modulo check
+/// on world-space Z (which double-fired and ignored track curvature).
+pub fn check_track_progress(
+    mut ev_generatemap: EventWriter<GenerateMapEvent>,
+    track: Res<TrackState>,
+    mut player: Query<(&Transform, &mut RollbackState), (With<LocalPlayer>, Without<Camera>)>,
+) {
+    let Ok((transform, mut rollback_state)) = player.get_single_mut() else {
+        return;
+    };
+
+    rollback_state.z_progress = transform.translation.z;
+
+    let distance_to_exit = (track.exit_position - transform.translation).length();
+    if distance_to_exit < STEP_LENGTH * STEPS_PER_CHUNK as f32 {
+        ev_generatemap.send(GenerateMapEvent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn control_points() -> (Vec3, Vec3, Vec3, Vec3) {
+        (
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 5.0, -10.0),
+            Vec3::new(10.0, 5.0, -30.0),
+            Vec3::new(10.0, 0.0, -40.0),
+        )
+    }
+
+    #[test]
+    fn bezier_point_at_t0_is_p0() {
+        let (p0, p1, p2, p3) = control_points();
+        assert_eq!(bezier_point(p0, p1, p2, p3, 0.0), p0);
+    }
+
+    #[test]
+    fn bezier_point_at_t1_is_p3() {
+        let (p0, p1, p2, p3) = control_points();
+        assert_eq!(bezier_point(p0, p1, p2, p3, 1.0), p3);
+    }
+
+    #[test]
+    fn bezier_tangent_at_t0_points_from_p0_toward_p1() {
+        let (p0, p1, p2, p3) = control_points();
+        let tangent = bezier_tangent(p0, p1, p2, p3, 0.0);
+        assert!(tangent.normalize().abs_diff_eq((p1 - p0).normalize(), 1e-6));
+    }
+
+    #[test]
+    fn bezier_tangent_at_t1_points_from_p2_toward_p3() {
+        let (p0, p1, p2, p3) = control_points();
+        let tangent = bezier_tangent(p0, p1, p2, p3, 1.0);
+        assert!(tangent.normalize().abs_diff_eq((p3 - p2).normalize(), 1e-6));
+    }
+}